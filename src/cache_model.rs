@@ -0,0 +1,193 @@
+//! An in-memory mirror of the desktop's accessible tree.
+//!
+//! Screen readers keep a local copy of the accessible tree so they don't
+//! have to round-trip to the bus for every query; this module is that
+//! cache. It is populated from `Cache::Add`/`Cache::Remove` events and
+//! lets callers look items up or walk the tree without touching D-Bus.
+
+use std::collections::HashMap;
+
+use atspi::{CacheItem, ObjectRef};
+use zbus::names::OwnedUniqueName;
+use zbus::zvariant::OwnedObjectPath;
+
+/// Uniquely identifies an accessible: the bus name that owns it and its
+/// object path on that bus.
+pub type CacheKey = (OwnedUniqueName, OwnedObjectPath);
+
+fn key_of(object: &ObjectRef) -> CacheKey {
+    (object.name.clone(), object.path.clone())
+}
+
+/// A live mirror of the accessible tree, keyed on `(bus_name, object_path)`.
+#[derive(Debug, Default)]
+pub struct CacheModel {
+    items: HashMap<CacheKey, CacheItem>,
+}
+
+impl CacheModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a freshly-seen `CacheItem`, replacing whatever was cached
+    /// for its key before.
+    pub fn add(&mut self, item: CacheItem) {
+        let key = key_of(&item.object);
+        self.items.insert(key, item);
+    }
+
+    /// Remove the accessible identified by `key`, along with any
+    /// descendants still present in the cache.
+    pub fn remove(&mut self, key: &CacheKey) {
+        let mut condemned = vec![key.clone()];
+        let mut i = 0;
+        while i < condemned.len() {
+            let current = condemned[i].clone();
+            for (child_key, item) in &self.items {
+                if key_of(&item.parent) == current && !condemned.contains(child_key) {
+                    condemned.push(child_key.clone());
+                }
+            }
+            i += 1;
+        }
+        for k in &condemned {
+            self.items.remove(k);
+        }
+    }
+
+    /// Look up the cached item for `key`, if any.
+    pub fn get(&self, key: &CacheKey) -> Option<&CacheItem> {
+        self.items.get(key)
+    }
+
+    /// The children of `key`, ordered by their `index` field.
+    ///
+    /// Excludes `key` itself: application roots legitimately reference
+    /// themselves as their own parent, and without this check such a
+    /// root would show up as one of its own children.
+    pub fn children_of(&self, key: &CacheKey) -> Vec<&CacheItem> {
+        let mut children: Vec<&CacheItem> = self
+            .items
+            .values()
+            .filter(|item| &key_of(&item.parent) == key && &key_of(&item.object) != key)
+            .collect();
+        children.sort_by_key(|item| item.index);
+        children
+    }
+
+    /// The cached item whose parent is not itself cached, i.e. the root
+    /// of the tree as far as this cache can see. Also matches an item
+    /// that is its own parent, since a self-parented root's parent key
+    /// is trivially "cached" (it's the item itself).
+    pub fn root(&self) -> Option<&CacheItem> {
+        self.items.values().find(|item| {
+            let parent_key = key_of(&item.parent);
+            parent_key == key_of(&item.object) || !self.items.contains_key(&parent_key)
+        })
+    }
+
+    /// The number of accessibles currently cached.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterate over every cached `(key, item)` pair.
+    pub fn iter(&self) -> impl Iterator<Item = (&CacheKey, &CacheItem)> {
+        self.items.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atspi::Role;
+
+    fn object_ref(bus_name: &str, path: &str) -> ObjectRef {
+        ObjectRef {
+            name: OwnedUniqueName::try_from(bus_name.to_string()).unwrap(),
+            path: OwnedObjectPath::try_from(path.to_string()).unwrap(),
+        }
+    }
+
+    fn item(path: &str, parent_path: &str, index: i32) -> CacheItem {
+        let object = object_ref(":1.0", path);
+        CacheItem {
+            object: object.clone(),
+            app: object,
+            parent: object_ref(":1.0", parent_path),
+            index,
+            children: 0,
+            ifaces: Default::default(),
+            role: Role::Unknown,
+            name: path.to_string(),
+            states: Default::default(),
+        }
+    }
+
+    #[test]
+    fn remove_evicts_descendants() {
+        let mut cache = CacheModel::new();
+        cache.add(item("/root", "/root", 0));
+        cache.add(item("/root/child", "/root", 0));
+        cache.add(item("/root/child/grandchild", "/root/child", 0));
+        cache.add(item("/root/sibling", "/root", 1));
+        assert_eq!(cache.len(), 4);
+
+        cache.remove(&key_of(&object_ref(":1.0", "/root/child")));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key_of(&object_ref(":1.0", "/root"))).is_some());
+        assert!(cache
+            .get(&key_of(&object_ref(":1.0", "/root/sibling")))
+            .is_some());
+        assert!(cache
+            .get(&key_of(&object_ref(":1.0", "/root/child")))
+            .is_none());
+        assert!(cache
+            .get(&key_of(&object_ref(":1.0", "/root/child/grandchild")))
+            .is_none());
+    }
+
+    #[test]
+    fn children_of_sorted_by_index() {
+        let mut cache = CacheModel::new();
+        // A self-parented root, as real application roots are: it must
+        // not show up among its own children.
+        cache.add(item("/root", "/root", 0));
+        cache.add(item("/root/c", "/root", 2));
+        cache.add(item("/root/a", "/root", 0));
+        cache.add(item("/root/b", "/root", 1));
+
+        let children = cache.children_of(&key_of(&object_ref(":1.0", "/root")));
+        let paths: Vec<&str> = children
+            .iter()
+            .map(|item| item.object.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["/root/a", "/root/b", "/root/c"]);
+    }
+
+    #[test]
+    fn root_is_item_with_uncached_parent() {
+        let mut cache = CacheModel::new();
+        cache.add(item("/root/child", "/root", 0));
+        cache.add(item("/root/child/grandchild", "/root/child", 0));
+
+        let root = cache.root().expect("a root should be found");
+        assert_eq!(root.object.path.as_str(), "/root/child");
+    }
+
+    #[test]
+    fn root_is_found_when_self_parented() {
+        let mut cache = CacheModel::new();
+        cache.add(item("/root", "/root", 0));
+        cache.add(item("/root/child", "/root", 0));
+
+        let root = cache.root().expect("a root should be found");
+        assert_eq!(root.object.path.as_str(), "/root");
+    }
+}
@@ -0,0 +1,79 @@
+//! Command-line arguments.
+//!
+//! Kept intentionally small and dependency-free: this is a handful of
+//! flags, not a full CLI surface, so a hand-rolled parser is simpler
+//! than pulling in a framework for it.
+
+use std::path::PathBuf;
+
+use crate::filter::EventFilter;
+
+/// Parsed command-line invocation.
+#[derive(Debug, Default)]
+pub struct Cli {
+    /// `--record <file>`: append every observed event to this file.
+    pub record: Option<PathBuf>,
+    /// `--replay <file>`: read events from this file instead of the bus.
+    pub replay: Option<PathBuf>,
+    /// `--pid <n>` / `--name-pattern <glob>`: restrict to one application.
+    pub filter: EventFilter,
+    /// `--check-interval <secs>`: periodically diff the cache against
+    /// live proxy queries to catch cache drift.
+    pub check_interval: Option<u64>,
+    /// `--watch <family1,family2,...>`: opt into non-Cache event
+    /// families (see `sink::EVENT_FAMILIES`), beyond the Cache events
+    /// this tool always watches.
+    pub watch: Vec<String>,
+}
+
+impl Cli {
+    pub fn parse() -> Result<Self, String> {
+        let mut cli = Cli::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    let path = args.next().ok_or("--record requires a file path")?;
+                    cli.record = Some(PathBuf::from(path));
+                }
+                "--replay" => {
+                    let path = args.next().ok_or("--replay requires a file path")?;
+                    cli.replay = Some(PathBuf::from(path));
+                }
+                "--pid" => {
+                    let pid = args.next().ok_or("--pid requires a process id")?;
+                    cli.filter.pid =
+                        Some(pid.parse().map_err(|_| format!("not a valid pid: {pid}"))?);
+                }
+                "--name-pattern" => {
+                    let pattern = args.next().ok_or("--name-pattern requires a glob")?;
+                    cli.filter.name_pattern = Some(pattern);
+                }
+                "--check-interval" => {
+                    let secs = args.next().ok_or("--check-interval requires seconds")?;
+                    cli.check_interval = Some(
+                        secs.parse()
+                            .map_err(|_| format!("not a valid interval: {secs}"))?,
+                    );
+                }
+                "--watch" => {
+                    let families = args.next().ok_or("--watch requires a family list")?;
+                    for family in families.split(',') {
+                        if !crate::sink::EVENT_FAMILIES.contains(&family) {
+                            return Err(format!("unknown event family: {family}"));
+                        }
+                        cli.watch.push(family.to_string());
+                    }
+                }
+                other => return Err(format!("unrecognized argument: {other}")),
+            }
+        }
+
+        if cli.record.is_some() && cli.replay.is_some() {
+            return Err("--record and --replay are mutually exclusive".to_string());
+        }
+
+        Ok(cli)
+    }
+}
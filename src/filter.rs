@@ -0,0 +1,89 @@
+//! Filtering observed events down to a single application.
+//!
+//! `--pid <n>` and `--name-pattern <glob>` let the monitor be pointed at
+//! one misbehaving application instead of the whole desktop, which is
+//! what makes it usable for reproducing a single app's cache behaviour
+//! in bug reports.
+
+use zbus::fdo::DBusProxy;
+use zbus::Connection;
+
+/// Criteria an application must satisfy for its events to be kept.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub pid: Option<u32>,
+    pub name_pattern: Option<String>,
+}
+
+impl EventFilter {
+    pub fn is_empty(&self) -> bool {
+        self.pid.is_none() && self.name_pattern.is_none()
+    }
+
+    /// Whether the application owning `bus_name` (with its accessible
+    /// name already resolved) passes this filter.
+    pub async fn matches(&self, conn: &Connection, bus_name: &str, app_name: &str) -> bool {
+        if let Some(pattern) = &self.name_pattern {
+            if !glob_match(pattern, app_name) {
+                return false;
+            }
+        }
+
+        if let Some(want_pid) = self.pid {
+            match DBusProxy::new(conn).await {
+                Ok(dbus) => match dbus.get_connection_unix_process_id(bus_name).await {
+                    Ok(pid) if pid == want_pid => {}
+                    _ => return false,
+                },
+                Err(_) => return false,
+            }
+        }
+
+        true
+    }
+}
+
+/// A small `*`/`?` glob matcher, enough for matching application names
+/// without pulling in a dependency for it.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_inner(&pattern, &text)
+}
+
+fn glob_match_inner(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => {
+            glob_match_inner(&pattern[1..], text)
+                || (!text.is_empty() && glob_match_inner(pattern, &text[1..]))
+        }
+        Some('?') => !text.is_empty() && glob_match_inner(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match_inner(&pattern[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn matches_exact() {
+        assert!(glob_match("firefox", "firefox"));
+        assert!(!glob_match("firefox", "chromium"));
+    }
+
+    #[test]
+    fn matches_star() {
+        assert!(glob_match("fire*", "firefox"));
+        assert!(glob_match("*fox", "firefox"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("fire*", "chromium"));
+    }
+
+    #[test]
+    fn matches_question_mark() {
+        assert!(glob_match("gedi?", "gedit"));
+        assert!(!glob_match("gedi?", "gedi"));
+    }
+}
@@ -1,39 +1,201 @@
+mod cache_model;
+mod cli;
+mod filter;
+mod record;
+mod sink;
+mod validate;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
 use atspi::{
     connection::AccessibilityConnection,
-    events::{AddAccessibleEvent, CacheEvents, LegacyAddAccessibleEvent, RemoveAccessibleEvent},
+    events::{
+        AddAccessibleEvent, CacheEvents, DocumentEvents, FocusEvents, KeyboardEvents,
+        LegacyAddAccessibleEvent, MouseEvents, ObjectEvents, RemoveAccessibleEvent,
+        TerminalEvents, WindowEvents,
+    },
     proxy::{accessible::AccessibleProxy, application::ApplicationProxy},
     CacheItem, Event, Role,
 };
+use cache_model::CacheModel;
+use cli::Cli;
+use record::{EventRecord, Recorder};
+use sink::{EventSink, LoggingSink};
 use tokio_stream::StreamExt;
 use zbus::{self, MessageType};
 
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub(crate) type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 const APPLICATION_INTERFACE: &str = "org.a11y.atspi.Application";
 const ACCESSIBLE_INTERFACE: &str = "org.a11y.atspi.Accessible";
+const CACHE_INTERFACE: &str = "org.a11y.atspi.Cache";
+const CACHE_MEMBERS: [&str; 2] = ["AddAccessible", "RemoveAccessible"];
 
-async fn atspi_setup_connection() -> Result<AccessibilityConnection> {
+async fn atspi_setup_connection(watch: &[String]) -> Result<AccessibilityConnection> {
     // Get a connection to the AT-SPI D-Bus service
     let atspi: AccessibilityConnection = AccessibilityConnection::open().await?;
+    let conn = atspi.connection();
 
-    // Register for events with registryd & set match rules at the a11y bus
-    // (if applicable)
+    // Ask the bus daemon to only forward Cache signals we care about,
+    // instead of waking this task for every message on the a11y bus.
+    for member in CACHE_MEMBERS {
+        let rule = zbus::MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(CACHE_INTERFACE)?
+            .member(member)?
+            .build();
+        conn.add_match_rule(rule).await?;
+    }
+
+    // Register for events with registryd (if applicable)
     atspi.register_event::<AddAccessibleEvent>().await?;
     atspi.register_event::<LegacyAddAccessibleEvent>().await?;
     atspi.register_event::<RemoveAccessibleEvent>().await?;
 
+    // Opt-in families beyond Cache: object, window, focus, document,
+    // terminal, mouse, keyboard. AT-SPI apps only emit an event family at
+    // all once registryd tells them a listener wants it, so registration
+    // has to happen here alongside the bus-level match rule below, the
+    // same as the Cache events just above.
+    for family in watch {
+        match family.as_str() {
+            "object" => {
+                atspi.register_event::<ObjectEvents>().await?;
+            }
+            "window" => {
+                atspi.register_event::<WindowEvents>().await?;
+            }
+            "focus" => {
+                atspi.register_event::<FocusEvents>().await?;
+            }
+            "document" => {
+                atspi.register_event::<DocumentEvents>().await?;
+            }
+            "terminal" => {
+                atspi.register_event::<TerminalEvents>().await?;
+            }
+            "mouse" => {
+                atspi.register_event::<MouseEvents>().await?;
+            }
+            "keyboard" => {
+                atspi.register_event::<KeyboardEvents>().await?;
+            }
+            _ => {}
+        }
+
+        if let Some(interface) = sink::family_interface(family) {
+            let rule = zbus::MatchRule::builder()
+                .msg_type(MessageType::Signal)
+                .interface(interface)?
+                .build();
+            conn.add_match_rule(rule).await?;
+        }
+    }
+
     Ok(atspi)
 }
 
+/// Resolve an application's toolkit and display name, and apply the
+/// `--pid`/`--name-pattern` filter. Returns `None` if the event should
+/// be dropped rather than cached/recorded/printed.
+async fn resolve_app(
+    conn: &zbus::Connection,
+    cli: &Cli,
+    bus_name: &str,
+    obj_path: &str,
+) -> Result<Option<(String, String)>> {
+    let toolkit_name = match zbus::ProxyBuilder::<ApplicationProxy>::new(conn)
+        .interface(APPLICATION_INTERFACE)?
+        .path(obj_path)?
+        .destination(bus_name)?
+        .build()
+        .await
+    {
+        Ok(application_proxy) => application_proxy
+            .toolkit_name()
+            .await
+            .unwrap_or("Could not read toolkit property".to_string()),
+        Err(_) => "Could not build application proxy".to_string(),
+    };
+
+    let key = (
+        zbus::names::OwnedUniqueName::try_from(bus_name.to_string())?,
+        zbus::zvariant::OwnedObjectPath::try_from(obj_path.to_string())?,
+    );
+    let app_name = match validate::resolve(conn, &key).await {
+        Ok(accessible_proxy) => accessible_proxy
+            .name()
+            .await
+            .unwrap_or("Could not read name property".to_string()),
+        Err(_) => "Could not build accessible proxy".to_string(),
+    };
+
+    if !cli.filter.is_empty() && !cli.filter.matches(conn, bus_name, &app_name).await {
+        return Ok(None);
+    }
+
+    Ok(Some((toolkit_name, app_name)))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let atspi = atspi_setup_connection().await?;
+    let cli = Cli::parse()?;
+
+    if let Some(replay_path) = &cli.replay {
+        return record::replay(replay_path);
+    }
+
+    let atspi = atspi_setup_connection(&cli.watch).await?;
     let conn = atspi.connection();
+    let mut cache = CacheModel::new();
+    let mut sink = LoggingSink;
+    let recorder = Recorder::new();
+    let mut record_file = match &cli.record {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+
+    // The match rules installed in `atspi_setup_connection` mean the bus
+    // daemon only forwards the Cache signals, plus the signals of any
+    // `--watch` families, that we asked for.
+    let mut cache_signals = zbus::MessageStream::from(conn);
 
-    let mut raw_signals = zbus::MessageStream::from(conn)
-        .filter(|msg| msg.is_ok() && msg.as_ref().unwrap().message_type() == MessageType::Signal);
+    // `None` when drift checking isn't requested, so the `select!` below
+    // waits on a future that never resolves and degrades to plain event
+    // handling.
+    let mut drift_check = cli
+        .check_interval
+        .map(|secs| tokio::time::interval(Duration::from_secs(secs)));
+
+    loop {
+        let msg = tokio::select! {
+            msg = cache_signals.next() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = async {
+                match &mut drift_check {
+                    Some(interval) => interval.tick().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                let stale = validate::check_drift(conn, &cache).await;
+                if stale.is_empty() {
+                    println!("drift check: cache agrees with the live tree");
+                } else {
+                    for (key, drifts) in stale {
+                        println!("drift check: {} {} disagrees with live state:", key.0, key.1);
+                        for drift in drifts {
+                            println!("  {drift}");
+                        }
+                    }
+                }
+                continue;
+            }
+        };
 
-    while let Some(msg) = raw_signals.next().await {
         match msg {
             Ok(msg) => {
                 if let Ok(event) = Event::try_from(&*msg) {
@@ -46,27 +208,46 @@ async fn main() -> Result<()> {
                             );
 
                             let AddAccessibleEvent { node_added, .. } = event.clone();
-                            let CacheItem { app, .. } = node_added;
+                            let CacheItem {
+                                object,
+                                app,
+                                parent,
+                                index,
+                                role: item_role,
+                                name: item_name,
+                                ..
+                            } = node_added.clone();
 
                             let bus_name = app.name;
                             let obj_path = app.path;
                             println!("Root object of Cache event bus_name: {bus_name}, obj_path: {obj_path}");
 
-                            // Application Proxy for `app`:
-                            if let Ok(application_proxy) =
-                                zbus::ProxyBuilder::<ApplicationProxy>::new(conn)
-                                    .interface(APPLICATION_INTERFACE)?
-                                    .path(obj_path.as_str())?
-                                    .destination(bus_name.as_str())?
-                                    .build()
-                                    .await
-                            {
-                                let toolkit_name = application_proxy
-                                    .toolkit_name()
-                                    .await
-                                    .unwrap_or("Could not read toolkit property".to_string());
-                                println!("toolkit: {toolkit_name}");
+                            let Some((toolkit_name, app_name)) =
+                                resolve_app(conn, &cli, bus_name.as_str(), obj_path.as_str())
+                                    .await?
+                            else {
+                                continue;
                             };
+                            println!("toolkit: {toolkit_name}");
+
+                            cache.add(node_added);
+                            println!("cache now holds {} accessibles", cache.len());
+
+                            if let Some(file) = &mut record_file {
+                                let rec = recorder.stamp(EventRecord {
+                                    offset_ms: 0,
+                                    member: "AddAccessible".to_string(),
+                                    bus_name: object.name.to_string(),
+                                    object_path: object.path.to_string(),
+                                    role: item_role.to_string(),
+                                    name: item_name,
+                                    toolkit: toolkit_name,
+                                    parent_bus_name: parent.name.to_string(),
+                                    parent_object_path: parent.path.to_string(),
+                                    index,
+                                });
+                                writeln!(file, "{rec}")?;
+                            }
 
                             // AccessibleProxy for `app`:
                             if let Ok(accessible_proxy) =
@@ -77,11 +258,7 @@ async fn main() -> Result<()> {
                                     .build()
                                     .await
                             {
-                                let name: String = accessible_proxy
-                                    .name()
-                                    .await
-                                    .unwrap_or("Could not read name property".to_string());
-                                println!("name: {name}");
+                                println!("name: {app_name}");
 
                                 let description: String = accessible_proxy
                                     .description()
@@ -97,23 +274,84 @@ async fn main() -> Result<()> {
                             // println!(": {:?}", event);
                         }
 
-                        Event::Cache(CacheEvents::Remove(_event)) => {
+                        Event::Cache(CacheEvents::Remove(event)) => {
                             println!(
                                 "RemoveAccessible: DBus body signature: {}",
                                 msg.body_signature().unwrap().as_str()
                             );
 
-                            //  println!(": {:?}", event);
+                            let bus_name = event.node_removed.name.to_string();
+                            let obj_path = event.node_removed.path.to_string();
+                            let key = (event.node_removed.name, event.node_removed.path);
+                            cache.remove(&key);
+                            println!("cache now holds {} accessibles", cache.len());
+
+                            if let Some(file) = &mut record_file {
+                                let rec = recorder.stamp(EventRecord {
+                                    offset_ms: 0,
+                                    member: "RemoveAccessible".to_string(),
+                                    bus_name,
+                                    object_path: obj_path,
+                                    role: String::new(),
+                                    name: String::new(),
+                                    toolkit: String::new(),
+                                    parent_bus_name: String::new(),
+                                    parent_object_path: String::new(),
+                                    index: -1,
+                                });
+                                writeln!(file, "{rec}")?;
+                            }
                         }
 
-                        Event::Cache(CacheEvents::LegacyAdd(_event)) => {
+                        Event::Cache(CacheEvents::LegacyAdd(event)) => {
                             println!(
                                 "LegacyAddAccessible: DBus body signature: {}",
                                 msg.body_signature().unwrap().as_str()
                             );
 
-                            // println!(": {:?}", event);
+                            let CacheItem {
+                                object,
+                                app,
+                                parent,
+                                index,
+                                role: item_role,
+                                name: item_name,
+                                ..
+                            } = event.node_added.clone();
+
+                            let Some((toolkit_name, _app_name)) =
+                                resolve_app(conn, &cli, app.name.as_str(), app.path.as_str())
+                                    .await?
+                            else {
+                                continue;
+                            };
+
+                            cache.add(event.node_added);
+                            println!("cache now holds {} accessibles", cache.len());
+
+                            if let Some(file) = &mut record_file {
+                                let rec = recorder.stamp(EventRecord {
+                                    offset_ms: 0,
+                                    member: "LegacyAddAccessible".to_string(),
+                                    bus_name: object.name.to_string(),
+                                    object_path: object.path.to_string(),
+                                    role: item_role.to_string(),
+                                    name: item_name,
+                                    toolkit: toolkit_name,
+                                    parent_bus_name: parent.name.to_string(),
+                                    parent_object_path: parent.path.to_string(),
+                                    index,
+                                });
+                                writeln!(file, "{rec}")?;
+                            }
                         }
+                        Event::Object(event) => sink.on_object(&event, &mut cache),
+                        Event::Window(event) => sink.on_window(&event),
+                        Event::Focus(event) => sink.on_focus(&event),
+                        Event::Document(event) => sink.on_document(&event),
+                        Event::Terminal(event) => sink.on_terminal(&event),
+                        Event::Mouse(event) => sink.on_mouse(&event),
+                        Event::Keyboard(event) => sink.on_keyboard(&event),
                         _ => {} // We do not care about other events
                     }
                 }
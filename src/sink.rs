@@ -0,0 +1,114 @@
+//! Handlers for the non-Cache event families atspi exposes.
+//!
+//! `CacheEvents` get dedicated handling in `main` because they drive the
+//! `CacheModel` directly. Everything else (object, window, focus,
+//! document, terminal, mouse, keyboard) is dispatched through this
+//! trait instead, so a caller can plug in handling per family without
+//! touching the dispatch loop.
+
+use atspi::events::{
+    DocumentEvents, FocusEvents, KeyboardEvents, MouseEvents, ObjectEvents, TerminalEvents,
+    WindowEvents,
+};
+
+use crate::cache_model::CacheModel;
+
+/// A pluggable handler for one family of atspi events.
+///
+/// Every method defaults to a no-op, so a sink only needs to implement
+/// the families it actually cares about.
+pub trait EventSink {
+    fn on_object(&mut self, _event: &ObjectEvents, _cache: &mut CacheModel) {}
+    fn on_window(&mut self, _event: &WindowEvents) {}
+    fn on_focus(&mut self, _event: &FocusEvents) {}
+    fn on_document(&mut self, _event: &DocumentEvents) {}
+    fn on_terminal(&mut self, _event: &TerminalEvents) {}
+    fn on_mouse(&mut self, _event: &MouseEvents) {}
+    fn on_keyboard(&mut self, _event: &KeyboardEvents) {}
+}
+
+/// The default sink: logs every event, and for the two object events
+/// that concern a cached item (state and children changes), correlates
+/// them back to the `CacheModel`.
+#[derive(Debug, Default)]
+pub struct LoggingSink;
+
+impl EventSink for LoggingSink {
+    fn on_object(&mut self, event: &ObjectEvents, cache: &mut CacheModel) {
+        match event {
+            ObjectEvents::StateChanged(changed) => {
+                let key = (changed.item.name.clone(), changed.item.path.clone());
+                match cache.get(&key) {
+                    Some(_) => println!(
+                        "object:state-changed for cached accessible {} {}: {} -> {}",
+                        key.0, key.1, changed.state, changed.enabled
+                    ),
+                    None => println!(
+                        "object:state-changed for uncached accessible {} {}",
+                        key.0, key.1
+                    ),
+                }
+            }
+            ObjectEvents::ChildrenChanged(changed) => {
+                let key = (changed.item.name.clone(), changed.item.path.clone());
+                let cached = if cache.get(&key).is_some() {
+                    "cached"
+                } else {
+                    "uncached"
+                };
+                println!(
+                    "object:children-changed ({}) for {cached} accessible {} {}; cache holds {} accessibles",
+                    changed.operation,
+                    key.0,
+                    key.1,
+                    cache.len()
+                );
+            }
+            other => println!("object event: {other:?}"),
+        }
+    }
+
+    fn on_window(&mut self, event: &WindowEvents) {
+        println!("window event: {event:?}");
+    }
+
+    fn on_focus(&mut self, event: &FocusEvents) {
+        println!("focus event: {event:?}");
+    }
+
+    fn on_document(&mut self, event: &DocumentEvents) {
+        println!("document event: {event:?}");
+    }
+
+    fn on_terminal(&mut self, event: &TerminalEvents) {
+        println!("terminal event: {event:?}");
+    }
+
+    fn on_mouse(&mut self, event: &MouseEvents) {
+        println!("mouse event: {event:?}");
+    }
+
+    fn on_keyboard(&mut self, event: &KeyboardEvents) {
+        println!("keyboard event: {event:?}");
+    }
+}
+
+/// The event families a caller can opt into beyond `Cache`, matching the
+/// `org.a11y.atspi.Event.*` D-Bus interfaces.
+pub const EVENT_FAMILIES: [&str; 7] = [
+    "object", "window", "focus", "document", "terminal", "mouse", "keyboard",
+];
+
+/// The D-Bus interface a family is announced under.
+pub fn family_interface(family: &str) -> Option<&'static str> {
+    Some(match family {
+        "object" => "org.a11y.atspi.Event.Object",
+        "window" => "org.a11y.atspi.Event.Window",
+        "focus" => "org.a11y.atspi.Event.Focus",
+        "document" => "org.a11y.atspi.Event.Document",
+        "terminal" => "org.a11y.atspi.Event.Terminal",
+        "mouse" => "org.a11y.atspi.Event.Mouse",
+        "keyboard" => "org.a11y.atspi.Event.Keyboard",
+        _ => return None,
+    })
+}
@@ -0,0 +1,104 @@
+//! Cross-validates cached entries against live proxy queries.
+//!
+//! Applications announce `Add`/`Remove` events with an `index`/`children`
+//! count that can race with the real tree, so the cache this crate builds
+//! can drift from what the application actually reports. This module is
+//! the "sigmon" half of the name: it diffs the cache against a fresh
+//! `AccessibleProxy` query and reports what no longer agrees.
+
+use atspi::proxy::accessible::AccessibleProxy;
+use atspi::Role;
+use zbus::Connection;
+
+use crate::cache_model::{CacheKey, CacheModel};
+use crate::Result;
+
+const ACCESSIBLE_INTERFACE: &str = "org.a11y.atspi.Accessible";
+
+/// Build an `AccessibleProxy` for the accessible identified by `key`.
+///
+/// Lifted out of `main`'s event handling so both the live monitor and
+/// this drift checker resolve accessibles the same way.
+pub async fn resolve<'a>(conn: &Connection, key: &CacheKey) -> Result<AccessibleProxy<'a>> {
+    let (bus_name, path) = key;
+    let proxy = zbus::ProxyBuilder::<AccessibleProxy>::new(conn)
+        .interface(ACCESSIBLE_INTERFACE)?
+        .path(path.as_str())?
+        .destination(bus_name.as_str())?
+        .build()
+        .await?;
+    Ok(proxy)
+}
+
+/// A single field where a cached item disagrees with the live accessible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    ChildCount { cached: i32, live: i32 },
+    Role { cached: Role, live: Role },
+    Name { cached: String, live: String },
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::ChildCount { cached, live } => {
+                write!(f, "child count: cached={cached} live={live}")
+            }
+            Drift::Role { cached, live } => write!(f, "role: cached={cached} live={live}"),
+            Drift::Name { cached, live } => write!(f, "name: cached={cached:?} live={live:?}"),
+        }
+    }
+}
+
+/// Compare every item `cache` holds against a fresh proxy query, and
+/// report the accessibles that have drifted, along with how.
+///
+/// This only covers `children`, `role`, and `name`: `CacheItem` carries
+/// no `description` field, so there is nothing cached to diff
+/// `AccessibleProxy::description()` against.
+pub async fn check_drift(conn: &Connection, cache: &CacheModel) -> Vec<(CacheKey, Vec<Drift>)> {
+    let mut stale = Vec::new();
+
+    for (key, item) in cache.iter() {
+        let Ok(proxy) = resolve(conn, key).await else {
+            // The accessible is gone from the bus entirely; Remove should
+            // catch that separately, so this isn't field drift.
+            continue;
+        };
+
+        let mut drifts = Vec::new();
+
+        if let Ok(live_children) = proxy.child_count().await {
+            if live_children != item.children {
+                drifts.push(Drift::ChildCount {
+                    cached: item.children,
+                    live: live_children,
+                });
+            }
+        }
+
+        if let Ok(live_role) = proxy.get_role().await {
+            if live_role != item.role {
+                drifts.push(Drift::Role {
+                    cached: item.role,
+                    live: live_role,
+                });
+            }
+        }
+
+        if let Ok(live_name) = proxy.name().await {
+            if live_name != item.name {
+                drifts.push(Drift::Name {
+                    cached: item.name.clone(),
+                    live: live_name,
+                });
+            }
+        }
+
+        if !drifts.is_empty() {
+            stale.push((key.clone(), drifts));
+        }
+    }
+
+    stale
+}